@@ -31,86 +31,298 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use crate::consts::*;
+use aes::{Aes128, Aes192, Aes256};
 use des::{
     block_cipher_trait::{generic_array::GenericArray, BlockCipher},
     TdesEde3,
 };
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use zeroize::Zeroize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
 
-/// 3DES keys. The three subkeys are concatenated.
-pub struct DesKey([u8; DES_LEN_3DES]);
+/// PIV algorithm tag for a Triple-DES (EDE3) management key.
+pub const YKPIV_ALGO_3DES: u8 = 0x03;
 
-impl DesKey {
+/// PIV algorithm tag for an AES-128 management key.
+pub const YKPIV_ALGO_AES128: u8 = 0x08;
+
+/// PIV algorithm tag for an AES-192 management key.
+pub const YKPIV_ALGO_AES192: u8 = 0x0A;
+
+/// PIV algorithm tag for an AES-256 management key.
+pub const YKPIV_ALGO_AES256: u8 = 0x0C;
+
+/// Largest native block size across the supported management-key ciphers
+/// (8 bytes for 3DES, 16 bytes for AES); used to size stack block buffers.
+const MAX_BLOCK_LEN: usize = 16;
+
+/// Error returned by the block-mode helpers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CryptoError {
+    /// The IV length or input length was not a whole multiple of the block
+    /// size, or the input was empty.
+    InvalidLength,
+}
+
+/// PIV management key.
+///
+/// Historically the management key was always a 3DES (EDE3) key, but YubiKey
+/// firmware 5.4.2 and newer additionally allows it to be an AES-128/192/256
+/// key. Each variant carries its PIV algorithm tag byte and operates on its
+/// cipher's native block size (8 bytes for 3DES, 16 bytes for AES), which the
+/// GENERAL AUTHENTICATE challenge-response witness length depends on.
+pub enum MgmKey {
+    /// Triple-DES (EDE3) management key.
+    Tdes([u8; DES_LEN_3DES]),
+
+    /// AES-128 management key.
+    Aes128([u8; 16]),
+
+    /// AES-192 management key.
+    Aes192([u8; 24]),
+
+    /// AES-256 management key.
+    Aes256([u8; 32]),
+}
+
+impl MgmKey {
+    /// Create a 3DES management key from its raw bytes.
     pub fn from_bytes(bytes: [u8; DES_LEN_3DES]) -> Self {
-        DesKey(bytes)
+        MgmKey::Tdes(bytes)
+    }
+
+    /// PIV algorithm tag for this management key.
+    pub fn algorithm(&self) -> u8 {
+        match self {
+            MgmKey::Tdes(_) => YKPIV_ALGO_3DES,
+            MgmKey::Aes128(_) => YKPIV_ALGO_AES128,
+            MgmKey::Aes192(_) => YKPIV_ALGO_AES192,
+            MgmKey::Aes256(_) => YKPIV_ALGO_AES256,
+        }
+    }
+
+    /// Native cipher block size in bytes.
+    pub fn block_size(&self) -> usize {
+        match self {
+            MgmKey::Tdes(_) => DES_LEN_DES,
+            MgmKey::Aes128(_) | MgmKey::Aes192(_) | MgmKey::Aes256(_) => 16,
+        }
+    }
+
+    /// Encrypt the single block `input` into `output`, dispatching on the
+    /// key's cipher.
+    ///
+    /// The block length must equal [`MgmKey::block_size`].
+    pub fn encrypt(&self, input: &[u8], output: &mut [u8]) {
+        output.copy_from_slice(input);
+        let block = GenericArray::from_mut_slice(output);
+        match self {
+            MgmKey::Tdes(k) => TdesEde3::new(GenericArray::from_slice(k)).encrypt_block(block),
+            MgmKey::Aes128(k) => Aes128::new(GenericArray::from_slice(k)).encrypt_block(block),
+            MgmKey::Aes192(k) => Aes192::new(GenericArray::from_slice(k)).encrypt_block(block),
+            MgmKey::Aes256(k) => Aes256::new(GenericArray::from_slice(k)).encrypt_block(block),
+        }
+    }
+
+    /// Decrypt the single block `input` into `output`, dispatching on the
+    /// key's cipher.
+    ///
+    /// The block length must equal [`MgmKey::block_size`].
+    pub fn decrypt(&self, input: &[u8], output: &mut [u8]) {
+        output.copy_from_slice(input);
+        let block = GenericArray::from_mut_slice(output);
+        match self {
+            MgmKey::Tdes(k) => TdesEde3::new(GenericArray::from_slice(k)).decrypt_block(block),
+            MgmKey::Aes128(k) => Aes128::new(GenericArray::from_slice(k)).decrypt_block(block),
+            MgmKey::Aes192(k) => Aes192::new(GenericArray::from_slice(k)).decrypt_block(block),
+            MgmKey::Aes256(k) => Aes256::new(GenericArray::from_slice(k)).decrypt_block(block),
+        }
+    }
+
+    /// Encrypt `buffer` in place under CBC mode, chaining with `iv`.
+    ///
+    /// Each plaintext block is XORed with the previous ciphertext block (the
+    /// IV for the first block) before encryption. `buffer` must be a non-empty,
+    /// whole number of [`MgmKey::block_size`]-byte blocks and `iv` must be exactly
+    /// one block long, otherwise [`CryptoError::InvalidLength`] is returned.
+    pub fn encrypt_cbc(&self, iv: &[u8], buffer: &mut [u8]) -> Result<(), CryptoError> {
+        let bs = self.block_size();
+        if iv.len() != bs || buffer.is_empty() || buffer.len() % bs != 0 {
+            return Err(CryptoError::InvalidLength);
+        }
+
+        let mut prev = Zeroizing::new([0u8; MAX_BLOCK_LEN]);
+        prev[..bs].copy_from_slice(iv);
+        let mut block = Zeroizing::new([0u8; MAX_BLOCK_LEN]);
+
+        for chunk in buffer.chunks_mut(bs) {
+            for (b, p) in chunk.iter_mut().zip(prev[..bs].iter()) {
+                *b ^= *p;
+            }
+            self.encrypt(chunk, &mut block[..bs]);
+            chunk.copy_from_slice(&block[..bs]);
+            prev[..bs].copy_from_slice(&block[..bs]);
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt `buffer` in place under CBC mode, chaining with `iv`.
+    ///
+    /// Inverse of [`MgmKey::encrypt_cbc`]: each block is decrypted and then
+    /// XORed with the previous ciphertext block (the IV for the first block).
+    /// The same length rules apply.
+    pub fn decrypt_cbc(&self, iv: &[u8], buffer: &mut [u8]) -> Result<(), CryptoError> {
+        let bs = self.block_size();
+        if iv.len() != bs || buffer.is_empty() || buffer.len() % bs != 0 {
+            return Err(CryptoError::InvalidLength);
+        }
+
+        let mut prev = Zeroizing::new([0u8; MAX_BLOCK_LEN]);
+        prev[..bs].copy_from_slice(iv);
+        let mut cipher = Zeroizing::new([0u8; MAX_BLOCK_LEN]);
+        let mut block = Zeroizing::new([0u8; MAX_BLOCK_LEN]);
+
+        for chunk in buffer.chunks_mut(bs) {
+            cipher[..bs].copy_from_slice(chunk);
+            self.decrypt(chunk, &mut block[..bs]);
+            for (b, p) in block[..bs].iter_mut().zip(prev[..bs].iter()) {
+                *b ^= *p;
+            }
+            chunk.copy_from_slice(&block[..bs]);
+            prev[..bs].copy_from_slice(&cipher[..bs]);
+        }
+
+        Ok(())
     }
 }
 
-impl AsRef<[u8; 24]> for DesKey {
-    fn as_ref(&self) -> &[u8; 24] {
-        &self.0
+impl AsRef<[u8]> for MgmKey {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            MgmKey::Tdes(k) => &k[..],
+            MgmKey::Aes128(k) => &k[..],
+            MgmKey::Aes192(k) => &k[..],
+            MgmKey::Aes256(k) => &k[..],
+        }
     }
 }
 
-impl Zeroize for DesKey {
+impl Zeroize for MgmKey {
     fn zeroize(&mut self) {
-        self.0.zeroize();
+        match self {
+            MgmKey::Tdes(k) => k.zeroize(),
+            MgmKey::Aes128(k) => k.zeroize(),
+            MgmKey::Aes192(k) => k.zeroize(),
+            MgmKey::Aes256(k) => k.zeroize(),
+        }
     }
 }
 
-impl Drop for DesKey {
+impl Drop for MgmKey {
     fn drop(&mut self) {
         self.zeroize();
     }
 }
 
-/// Encrypt with DES key
-#[allow(clippy::trivially_copy_pass_by_ref)]
-pub fn des_encrypt(key: &DesKey, input: &[u8; DES_LEN_DES], output: &mut [u8; DES_LEN_DES]) {
-    output.copy_from_slice(input);
-    TdesEde3::new(GenericArray::from_slice(&key.0))
-        .encrypt_block(GenericArray::from_mut_slice(output));
+/// Weak and semi weak keys as taken from
+/// %A D.W. Davies
+/// %A W.L. Price
+/// %T Security for Computer Networks
+/// %I John Wiley & Sons
+/// %D 1984
+const WEAK_KEYS: [[u8; DES_LEN_DES]; 16] = [
+    // weak keys
+    [0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01],
+    [0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE],
+    [0x1F, 0x1F, 0x1F, 0x1F, 0x0E, 0x0E, 0x0E, 0x0E],
+    [0xE0, 0xE0, 0xE0, 0xE0, 0xF1, 0xF1, 0xF1, 0xF1],
+    // semi-weak keys
+    [0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE],
+    [0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01],
+    [0x1F, 0xE0, 0x1F, 0xE0, 0x0E, 0xF1, 0x0E, 0xF1],
+    [0xE0, 0x1F, 0xE0, 0x1F, 0xF1, 0x0E, 0xF1, 0x0E],
+    [0x01, 0xE0, 0x01, 0xE0, 0x01, 0xF1, 0x01, 0xF1],
+    [0xE0, 0x01, 0xE0, 0x01, 0xF1, 0x01, 0xF1, 0x01],
+    [0x1F, 0xFE, 0x1F, 0xFE, 0x0E, 0xFE, 0x0E, 0xFE],
+    [0xFE, 0x1F, 0xFE, 0x1F, 0xFE, 0x0E, 0xFE, 0x0E],
+    [0x01, 0x1F, 0x01, 0x1F, 0x01, 0x0E, 0x01, 0x0E],
+    [0x1F, 0x01, 0x1F, 0x01, 0x0E, 0x01, 0x0E, 0x01],
+    [0xE0, 0xFE, 0xE0, 0xFE, 0xF1, 0xFE, 0xF1, 0xFE],
+    [0xFE, 0xE0, 0xFE, 0xE0, 0xFE, 0xF1, 0xFE, 0xF1],
+];
+
+/// Classification of a 3DES management key's cryptographic strength.
+///
+/// A 3DES (EDE3) key is three 8-byte DES subkeys `K1 || K2 || K3`. Beyond the
+/// classic weak/semi-weak single-DES keys it can also silently collapse to a
+/// much weaker cipher depending on how the subkeys relate: if all three are
+/// equal the key is effectively single DES, and if `K1 == K3` (with `K2`
+/// different) it is only two-key 3DES. After `Ok`, variants are ordered from
+/// most to least severe; classification reports the single most severe
+/// condition found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyStrength {
+    /// The key is a full-strength three-key 3DES key with correct parity.
+    Ok,
+
+    /// One of the subkeys is a known weak or semi-weak single-DES key.
+    Weak,
+
+    /// `K1 == K2 == K3`: the key reduces to single DES.
+    SingleDesEquivalent,
+
+    /// `K1 == K3` but `K2` differs: the key reduces to two-key 3DES.
+    TwoKeyEquivalent,
+
+    /// The key is otherwise acceptable but did not carry correct odd parity.
+    ParityWarning,
 }
 
-/// Decrypt with DES key
-#[allow(clippy::trivially_copy_pass_by_ref)]
-pub fn des_decrypt(key: &DesKey, input: &[u8; DES_LEN_DES], output: &mut [u8; DES_LEN_DES]) {
-    output.copy_from_slice(input);
-    TdesEde3::new(GenericArray::from_slice(&key.0))
-        .encrypt_block(GenericArray::from_mut_slice(output));
+/// Result of inspecting a 3DES management key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyInspection {
+    /// Most severe strength condition detected.
+    pub strength: KeyStrength,
+
+    /// Whether the supplied key already had correct odd parity on every byte
+    /// before parity normalization.
+    pub had_odd_parity: bool,
 }
 
-/// Is the given DES key weak?
-pub fn yk_des_is_weak_key(key: &[u8; DES_LEN_3DES]) -> bool {
-    /// Weak and semi weak keys as taken from
-    /// %A D.W. Davies
-    /// %A W.L. Price
-    /// %T Security for Computer Networks
-    /// %I John Wiley & Sons
-    /// %D 1984
-    const WEAK_KEYS: [[u8; DES_LEN_DES]; 16] = [
-        // weak keys
-        [0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01],
-        [0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE],
-        [0x1F, 0x1F, 0x1F, 0x1F, 0x0E, 0x0E, 0x0E, 0x0E],
-        [0xE0, 0xE0, 0xE0, 0xE0, 0xF1, 0xF1, 0xF1, 0xF1],
-        // semi-weak keys
-        [0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE],
-        [0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE, 0x01],
-        [0x1F, 0xE0, 0x1F, 0xE0, 0x0E, 0xF1, 0x0E, 0xF1],
-        [0xE0, 0x1F, 0xE0, 0x1F, 0xF1, 0x0E, 0xF1, 0x0E],
-        [0x01, 0xE0, 0x01, 0xE0, 0x01, 0xF1, 0x01, 0xF1],
-        [0xE0, 0x01, 0xE0, 0x01, 0xF1, 0x01, 0xF1, 0x01],
-        [0x1F, 0xFE, 0x1F, 0xFE, 0x0E, 0xFE, 0x0E, 0xFE],
-        [0xFE, 0x1F, 0xFE, 0x1F, 0xFE, 0x0E, 0xFE, 0x0E],
-        [0x01, 0x1F, 0x01, 0x1F, 0x01, 0x0E, 0x01, 0x0E],
-        [0x1F, 0x01, 0x1F, 0x01, 0x0E, 0x01, 0x0E, 0x01],
-        [0xE0, 0xFE, 0xE0, 0xFE, 0xF1, 0xFE, 0xF1, 0xFE],
-        [0xFE, 0xE0, 0xFE, 0xE0, 0xFE, 0xF1, 0xFE, 0xF1],
-    ];
+/// Constant-time equality of two equal-length byte slices.
+///
+/// Returns `1` if the slices are equal and `0` otherwise, without branching on
+/// or short-circuiting over the contents, so it does not leak where or whether
+/// a difference occurred.
+fn ct_bytes_eq(a: &[u8], b: &[u8]) -> u8 {
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+
+    // `diff == 0` -> 1, otherwise 0, branch-free: any nonzero byte sets bit 7
+    // of `diff | -diff`.
+    1 ^ ((diff | diff.wrapping_neg()) >> 7)
+}
+
+/// Inspect a 3DES management key for weak, degenerate, or mis-parity keys.
+///
+/// The table match, keying-option (subkey equality) checks, and parity check
+/// are all performed in constant time so the result does not reveal which
+/// subkey or table entry was responsible.
+pub fn yk_des_inspect_key(key: &[u8; DES_LEN_3DES]) -> KeyInspection {
+    // check odd parity of the supplied key before normalizing it
+    let mut had_odd_parity = 1u8;
+    for &b in key.iter() {
+        // fold the byte down to its parity bit: 1 if an odd number of bits set
+        let mut c = b;
+        c ^= c >> 4;
+        c ^= c >> 2;
+        c ^= c >> 1;
+        had_odd_parity &= c & 0x01;
+    }
 
     // set odd parity of key
     let mut tmp = [0u8; DES_LEN_3DES];
@@ -126,20 +338,55 @@ pub fn yk_des_is_weak_key(key: &[u8; DES_LEN_3DES]) -> bool {
         tmp[i] = (key[i] & 0xFE) | (if c & 0x01 == 0x01 { 0x00 } else { 0x01 });
     }
 
-    // check odd parity key against table by DES key block
-    let mut rv = false;
+    let k1 = &tmp[0..DES_LEN_DES];
+    let k2 = &tmp[DES_LEN_DES..2 * DES_LEN_DES];
+    let k3 = &tmp[2 * DES_LEN_DES..3 * DES_LEN_DES];
+
+    // check odd parity key against table by DES key block, accumulating every
+    // comparison (no early exit that would leak which subkey matched)
+    let mut weak = 0u8;
     for weak_key in WEAK_KEYS.iter() {
-        if weak_key == &tmp[0..DES_LEN_DES]
-            || weak_key == &tmp[DES_LEN_DES..2 * DES_LEN_DES]
-            || weak_key == &tmp[2 * DES_LEN_DES..3 * DES_LEN_DES]
-        {
-            rv = true;
-            break;
-        }
+        weak |= ct_bytes_eq(weak_key, k1);
+        weak |= ct_bytes_eq(weak_key, k2);
+        weak |= ct_bytes_eq(weak_key, k3);
     }
 
+    // classify keying option from constant-time subkey equality
+    let k1_eq_k2 = ct_bytes_eq(k1, k2);
+    let k2_eq_k3 = ct_bytes_eq(k2, k3);
+    let k1_eq_k3 = ct_bytes_eq(k1, k3);
+
     tmp.zeroize();
-    rv
+
+    let strength = if weak != 0 {
+        KeyStrength::Weak
+    } else if k1_eq_k2 & k2_eq_k3 == 1 {
+        KeyStrength::SingleDesEquivalent
+    } else if k1_eq_k3 == 1 {
+        KeyStrength::TwoKeyEquivalent
+    } else if had_odd_parity == 0 {
+        KeyStrength::ParityWarning
+    } else {
+        KeyStrength::Ok
+    };
+
+    KeyInspection {
+        strength,
+        had_odd_parity: had_odd_parity == 1,
+    }
+}
+
+/// Is the given DES key weak?
+///
+/// This is a convenience wrapper over [`yk_des_inspect_key`] that flags any key
+/// which is not full-strength three-key 3DES, including the degenerate
+/// single-DES and two-key collapses. A [`KeyStrength::ParityWarning`] on its
+/// own is not treated as weak, since parity is corrected before use.
+pub fn yk_des_is_weak_key(key: &[u8; DES_LEN_3DES]) -> bool {
+    !matches!(
+        yk_des_inspect_key(key).strength,
+        KeyStrength::Ok | KeyStrength::ParityWarning
+    )
 }
 
 /// Source of how a setting was configured
@@ -165,66 +412,207 @@ pub struct SettingBool {
     pub source: SettingSource,
 }
 
-/// Get a boolean config value
-pub fn _get_bool_config(key: &str) -> SettingBool {
-    let mut setting: SettingBool = SettingBool {
-        value: false,
-        source: SettingSource::Default,
-    };
+/// Setting integers
+#[derive(Copy, Clone, Debug)]
+pub struct SettingInt {
+    /// Integer value
+    pub value: i64,
 
-    if let Ok(f) = File::open("/etc/yubico/yubikeypiv.conf") {
-        for line in BufReader::new(f).lines() {
-            let line = match line {
-                Ok(line) => line,
-                _ => continue,
-            };
+    /// Source of the configuration setting (user/admin/default)
+    pub source: SettingSource,
+}
 
-            if line.starts_with('#') || line.starts_with('\r') || line.starts_with('\n') {
-                continue;
-            }
+/// Setting strings
+#[derive(Clone, Debug)]
+pub struct SettingString {
+    /// String value
+    pub value: String,
+
+    /// Source of the configuration setting (user/admin/default)
+    pub source: SettingSource,
+}
 
-            let (name, value) = {
-                let mut parts = line.splitn(1, '=');
-                let name = parts.next();
-                let value = parts.next();
-                match (name, value, parts.next()) {
-                    (Some(name), Some(value), None) => (name.trim(), value.trim()),
-                    _ => continue,
+/// Candidate configuration file paths, in precedence order (per-user first,
+/// then system-wide). The first file that defines a given key wins.
+fn config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    #[cfg(windows)]
+    {
+        // per-user and per-machine locations under the Yubico vendor directory
+        if let Ok(appdata) = env::var("APPDATA") {
+            paths.push(Path::new(&appdata).join("Yubico").join("yubikeypiv.conf"));
+        }
+
+        if let Ok(programdata) = env::var("PROGRAMDATA") {
+            paths.push(
+                Path::new(&programdata)
+                    .join("Yubico")
+                    .join("yubikeypiv.conf"),
+            );
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        // honor the XDG base directory spec, falling back to `$HOME/.config`
+        match env::var("XDG_CONFIG_HOME") {
+            Ok(ref xdg) if !xdg.is_empty() => {
+                paths.push(Path::new(xdg).join("yubico").join("yubikeypiv.conf"));
+            }
+            _ => {
+                if let Ok(home) = env::var("HOME") {
+                    paths.push(
+                        Path::new(&home)
+                            .join(".config")
+                            .join("yubico")
+                            .join("yubikeypiv.conf"),
+                    );
                 }
-            };
+            }
+        }
+
+        paths.push(PathBuf::from("/etc/yubico/yubikeypiv.conf"));
+    }
+
+    paths
+}
+
+/// Strip a single pair of matching surrounding quotes from a value, if present.
+fn unquote(value: &str) -> &str {
+    let bytes = value.as_bytes();
+    if value.len() >= 2 {
+        let first = bytes[0];
+        if (first == b'"' || first == b'\'') && bytes[value.len() - 1] == first {
+            return &value[1..value.len() - 1];
+        }
+    }
+
+    value
+}
+
+/// Find the raw (string) value for `key` within the contents of a single
+/// config file. Comment lines (`#`) and blank lines are skipped, names and
+/// values are whitespace-trimmed, and surrounding quotes are stripped.
+fn parse_config_value(contents: &str, key: &str) -> Option<String> {
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
 
-            if name == key {
-                setting.source = SettingSource::Admin;
-                setting.value = value == "1" || value == "true";
-                break;
+        let mut parts = trimmed.splitn(2, '=');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.trim() == key {
+                return Some(unquote(value.trim()).to_string());
             }
         }
     }
 
-    setting
+    None
+}
+
+/// Look up the raw value for `key` in the first config file that defines it.
+fn config_lookup(key: &str) -> Option<String> {
+    for path in config_paths() {
+        if let Ok(contents) = fs::read_to_string(&path) {
+            if let Some(value) = parse_config_value(&contents, key) {
+                return Some(value);
+            }
+        }
+    }
+
+    None
+}
+
+/// Look up the raw value for `key` in the environment.
+fn env_lookup(key: &str) -> Option<String> {
+    env::var(format!("YUBIKEY_PIV_{}", key)).ok()
+}
+
+/// Parse a configured boolean value.
+fn parse_bool(value: &str) -> bool {
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+/// Get a boolean config value
+pub fn _get_bool_config(key: &str) -> SettingBool {
+    match config_lookup(key) {
+        Some(value) => SettingBool {
+            value: parse_bool(&value),
+            source: SettingSource::Admin,
+        },
+        None => SettingBool {
+            value: false,
+            source: SettingSource::Default,
+        },
+    }
 }
 
 /// Get a setting boolean from an environment variable
 pub fn _get_bool_env(key: &str) -> SettingBool {
-    let mut setting: SettingBool = SettingBool {
-        value: false,
-        source: SettingSource::Default,
-    };
+    match env_lookup(key) {
+        Some(value) => SettingBool {
+            value: parse_bool(&value),
+            source: SettingSource::User,
+        },
+        None => SettingBool {
+            value: false,
+            source: SettingSource::Default,
+        },
+    }
+}
+
+/// Get a setting boolean, honoring user env > admin file > default.
+pub fn setting_get_bool(key: &str, def: bool) -> SettingBool {
+    let mut setting = _get_bool_env(key);
+
+    if setting.source == SettingSource::Default {
+        setting = _get_bool_config(key);
+    }
 
-    if let Ok(value) = env::var(format!("YUBIKEY_PIV_{}", key)) {
-        setting.source = SettingSource::User;
-        setting.value = value == "1" || value == "true";
+    if setting.source == SettingSource::Default {
+        setting.value = def;
     }
 
     setting
 }
 
-/// Get a setting boolean
-pub fn setting_get_bool(key: &str, def: bool) -> SettingBool {
-    let mut setting = _get_bool_config(key);
+/// Get an integer config value
+pub fn _get_int_config(key: &str) -> SettingInt {
+    match config_lookup(key).and_then(|v| v.parse::<i64>().ok()) {
+        Some(value) => SettingInt {
+            value,
+            source: SettingSource::Admin,
+        },
+        None => SettingInt {
+            value: 0,
+            source: SettingSource::Default,
+        },
+    }
+}
+
+/// Get a setting integer from an environment variable
+pub fn _get_int_env(key: &str) -> SettingInt {
+    match env_lookup(key).and_then(|v| v.parse::<i64>().ok()) {
+        Some(value) => SettingInt {
+            value,
+            source: SettingSource::User,
+        },
+        None => SettingInt {
+            value: 0,
+            source: SettingSource::Default,
+        },
+    }
+}
+
+/// Get a setting integer, honoring user env > admin file > default.
+pub fn setting_get_int(key: &str, def: i64) -> SettingInt {
+    let mut setting = _get_int_env(key);
 
     if setting.source == SettingSource::Default {
-        setting = _get_bool_env(key);
+        setting = _get_int_config(key);
     }
 
     if setting.source == SettingSource::Default {
@@ -232,4 +620,291 @@ pub fn setting_get_bool(key: &str, def: bool) -> SettingBool {
     }
 
     setting
+}
+
+/// Get a string config value
+pub fn _get_string_config(key: &str) -> Option<SettingString> {
+    config_lookup(key).map(|value| SettingString {
+        value,
+        source: SettingSource::Admin,
+    })
+}
+
+/// Get a setting string from an environment variable
+pub fn _get_string_env(key: &str) -> Option<SettingString> {
+    env_lookup(key).map(|value| SettingString {
+        value,
+        source: SettingSource::User,
+    })
+}
+
+/// Get a setting string, honoring user env > admin file > default.
+pub fn setting_get_string(key: &str, def: &str) -> SettingString {
+    _get_string_env(key)
+        .or_else(|| _get_string_config(key))
+        .unwrap_or_else(|| SettingString {
+            value: def.to_string(),
+            source: SettingSource::Default,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Canonical odd-parity DES test blocks, none of which appears in the
+    // weak/semi-weak table.
+    const BLOCK_A: [u8; DES_LEN_DES] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+    const BLOCK_B: [u8; DES_LEN_DES] = [0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0x01];
+    const BLOCK_C: [u8; DES_LEN_DES] = [0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10];
+
+    fn concat(k1: &[u8; DES_LEN_DES], k2: &[u8; DES_LEN_DES], k3: &[u8; DES_LEN_DES]) -> [u8; DES_LEN_3DES] {
+        let mut key = [0u8; DES_LEN_3DES];
+        key[0..DES_LEN_DES].copy_from_slice(k1);
+        key[DES_LEN_DES..2 * DES_LEN_DES].copy_from_slice(k2);
+        key[2 * DES_LEN_DES..3 * DES_LEN_DES].copy_from_slice(k3);
+        key
+    }
+
+    #[test]
+    fn full_strength_key_is_ok() {
+        let key = concat(&BLOCK_A, &BLOCK_B, &BLOCK_C);
+        let inspection = yk_des_inspect_key(&key);
+        assert_eq!(inspection.strength, KeyStrength::Ok);
+        assert!(inspection.had_odd_parity);
+        assert!(!yk_des_is_weak_key(&key));
+    }
+
+    #[test]
+    fn all_equal_subkeys_are_single_des() {
+        let key = concat(&BLOCK_A, &BLOCK_A, &BLOCK_A);
+        assert_eq!(
+            yk_des_inspect_key(&key).strength,
+            KeyStrength::SingleDesEquivalent
+        );
+        assert!(yk_des_is_weak_key(&key));
+    }
+
+    #[test]
+    fn k1_equals_k3_is_two_key() {
+        let key = concat(&BLOCK_A, &BLOCK_B, &BLOCK_A);
+        assert_eq!(
+            yk_des_inspect_key(&key).strength,
+            KeyStrength::TwoKeyEquivalent
+        );
+        assert!(yk_des_is_weak_key(&key));
+    }
+
+    #[test]
+    fn incorrect_parity_is_reported() {
+        // clear the low (parity) bit of the first byte so the block carries
+        // even parity; structure is otherwise a full-strength key
+        let mut tweaked = BLOCK_B;
+        tweaked[0] &= 0xFE;
+        let key = concat(&BLOCK_A, &tweaked, &BLOCK_C);
+        let inspection = yk_des_inspect_key(&key);
+        assert_eq!(inspection.strength, KeyStrength::ParityWarning);
+        assert!(!inspection.had_odd_parity);
+        // parity alone must not flag the key as weak
+        assert!(!yk_des_is_weak_key(&key));
+    }
+
+    #[test]
+    fn weak_table_entry_in_every_subkey_position() {
+        for entry in WEAK_KEYS.iter() {
+            for position in 0..3 {
+                let mut subkeys = [BLOCK_A, BLOCK_C, BLOCK_B];
+                subkeys[position] = *entry;
+                let key = concat(&subkeys[0], &subkeys[1], &subkeys[2]);
+                assert_eq!(
+                    yk_des_inspect_key(&key).strength,
+                    KeyStrength::Weak,
+                    "weak key {:02X?} in position {} not detected",
+                    entry,
+                    position
+                );
+                assert!(yk_des_is_weak_key(&key));
+            }
+        }
+    }
+
+    // Classic FIPS single-DES test vector: encrypting "Now is t" under key
+    // 0x0123456789ABCDEF yields 0x3FA40E8A984D4815. Replicating the DES key
+    // across all three 3DES subkeys makes EDE3 collapse to that single-DES
+    // result, giving a deterministic known-answer for the ECB inverse.
+    const TDES_KAT_KEY: [u8; DES_LEN_3DES] = [
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, // K1
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, // K2
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, // K3
+    ];
+    const TDES_KAT_PLAINTEXT: [u8; DES_LEN_DES] = [0x4E, 0x6F, 0x77, 0x20, 0x69, 0x73, 0x20, 0x74];
+    const TDES_KAT_CIPHERTEXT: [u8; DES_LEN_DES] = [0x3F, 0xA4, 0x0E, 0x8A, 0x98, 0x4D, 0x48, 0x15];
+
+    #[test]
+    fn tdes_ecb_inverse_known_answer() {
+        let key = MgmKey::from_bytes(TDES_KAT_KEY);
+
+        let mut ct = [0u8; DES_LEN_DES];
+        key.encrypt(&TDES_KAT_PLAINTEXT, &mut ct);
+        assert_eq!(ct, TDES_KAT_CIPHERTEXT);
+
+        // decrypt must be the true D-E-D inverse, recovering the plaintext
+        let mut pt = [0u8; DES_LEN_DES];
+        key.decrypt(&TDES_KAT_CIPHERTEXT, &mut pt);
+        assert_eq!(pt, TDES_KAT_PLAINTEXT);
+    }
+
+    #[test]
+    fn cbc_first_block_matches_ecb_with_zero_iv() {
+        // with an all-zero IV the first CBC block is just an ECB encryption
+        let key = MgmKey::from_bytes(TDES_KAT_KEY);
+        let iv = [0u8; DES_LEN_DES];
+        let mut buffer = TDES_KAT_PLAINTEXT;
+        key.encrypt_cbc(&iv, &mut buffer).unwrap();
+        assert_eq!(buffer, TDES_KAT_CIPHERTEXT);
+    }
+
+    #[test]
+    fn cbc_roundtrip_multiple_blocks() {
+        let key = MgmKey::from_bytes(TDES_KAT_KEY);
+        let iv = [0x0F, 0x1E, 0x2D, 0x3C, 0x4B, 0x5A, 0x69, 0x78];
+        let plaintext = [
+            0x4E, 0x6F, 0x77, 0x20, 0x69, 0x73, 0x20, 0x74, // "Now is t"
+            0x68, 0x65, 0x20, 0x74, 0x69, 0x6D, 0x65, 0x20, // "he time "
+        ];
+
+        let mut buffer = plaintext;
+        key.encrypt_cbc(&iv, &mut buffer).unwrap();
+        assert_ne!(buffer, plaintext);
+
+        key.decrypt_cbc(&iv, &mut buffer).unwrap();
+        assert_eq!(buffer, plaintext);
+    }
+
+    #[test]
+    fn cbc_rejects_bad_lengths() {
+        let key = MgmKey::from_bytes(TDES_KAT_KEY);
+        let iv = [0u8; DES_LEN_DES];
+
+        // buffer not a block multiple
+        let mut short = [0u8; DES_LEN_DES + 1];
+        assert_eq!(
+            key.encrypt_cbc(&iv, &mut short),
+            Err(CryptoError::InvalidLength)
+        );
+
+        // wrong IV length
+        let bad_iv = [0u8; DES_LEN_DES - 1];
+        let mut buffer = [0u8; DES_LEN_DES];
+        assert_eq!(
+            key.decrypt_cbc(&bad_iv, &mut buffer),
+            Err(CryptoError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn config_parsing_skips_comments_and_blanks() {
+        let contents = "\
+# a comment
+   # indented comment
+
+force_pin_policy = 1
+";
+        assert_eq!(
+            parse_config_value(contents, "force_pin_policy").as_deref(),
+            Some("1")
+        );
+        assert_eq!(parse_config_value(contents, "missing"), None);
+    }
+
+    #[test]
+    fn config_parsing_trims_whitespace_and_quotes() {
+        let contents = "\
+  spaced_key   =   value
+double = \"quoted value\"
+single = 'quoted value'
+only_leading = \"mismatched'
+";
+        assert_eq!(
+            parse_config_value(contents, "spaced_key").as_deref(),
+            Some("value")
+        );
+        assert_eq!(
+            parse_config_value(contents, "double").as_deref(),
+            Some("quoted value")
+        );
+        assert_eq!(
+            parse_config_value(contents, "single").as_deref(),
+            Some("quoted value")
+        );
+        // mismatched quotes are left untouched
+        assert_eq!(
+            parse_config_value(contents, "only_leading").as_deref(),
+            Some("\"mismatched'")
+        );
+    }
+
+    #[test]
+    fn config_first_assignment_wins() {
+        // first matching definition wins within a file
+        let contents = "key = first\nkey = second\n";
+        assert_eq!(parse_config_value(contents, "key").as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn source_precedence_user_env_over_admin_file() {
+        // isolate a per-user config dir and a unique key so this test does not
+        // race other settings lookups
+        let key = format!("test_prec_{}", std::process::id());
+        let env_name = format!("YUBIKEY_PIV_{}", key);
+
+        let dir = std::env::temp_dir().join(format!("ykpiv_cfg_{}", std::process::id()));
+        let conf_dir = dir.join("yubico");
+        std::fs::create_dir_all(&conf_dir).unwrap();
+        std::fs::write(
+            conf_dir.join("yubikeypiv.conf"),
+            format!("{} = 1\n", key),
+        )
+        .unwrap();
+
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+        #[cfg(windows)]
+        std::env::set_var("APPDATA", &dir);
+        std::env::remove_var(&env_name);
+
+        // admin file alone supplies the value
+        let setting = setting_get_bool(&key, false);
+        assert_eq!(setting.source, SettingSource::Admin);
+        assert!(setting.value);
+
+        // user env overrides the admin file
+        std::env::set_var(&env_name, "false");
+        let setting = setting_get_bool(&key, true);
+        assert_eq!(setting.source, SettingSource::User);
+        assert!(!setting.value);
+
+        // unknown key falls through to the default
+        let setting = setting_get_int("definitely_absent_key", 42);
+        assert_eq!(setting.source, SettingSource::Default);
+        assert_eq!(setting.value, 42);
+
+        std::env::remove_var(&env_name);
+        std::env::remove_var("XDG_CONFIG_HOME");
+        #[cfg(windows)]
+        std::env::remove_var("APPDATA");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn string_and_int_values_parse() {
+        let contents = "name = \"YubiKey 5\"\nretries = 3\n";
+        assert_eq!(
+            parse_config_value(contents, "name").as_deref(),
+            Some("YubiKey 5")
+        );
+        assert_eq!(
+            parse_config_value(contents, "retries").and_then(|v| v.parse::<i64>().ok()),
+            Some(3)
+        );
+    }
 }
\ No newline at end of file